@@ -24,6 +24,14 @@ impl<T> Collection<T> {
         self.items.push(item)
     }
 
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
     pub fn iter<'a>(&'a self) -> slice::Iter<'a, T> {
         self.into_iter()
     }
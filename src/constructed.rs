@@ -1,4 +1,5 @@
-use std::any::Any;
+use std::any::{self, Any};
+use std::error;
 use std::ops::{Deref, DerefMut};
 use std::sync::{Mutex, Arc, LockResult, PoisonError, MutexGuard};
 
@@ -68,16 +69,28 @@ impl AnyInstance {
         match self {
             AnyInstance::Isolated(parent) => {
                 Instance::Isolated(*parent.downcast()
-                    .expect("expected AnyInstance::Isolated to downcast into type"))
+                    .unwrap_or_else(|_| {
+                        panic!("expected AnyInstance::Isolated to downcast into {}",
+                               any::type_name::<T>())
+                    }))
             }
             AnyInstance::Shared(parent) => {
                 Instance::Shared(*parent.downcast()
-                    .expect("expected AnyInstance::Shared to downcast into type"))
+                    .unwrap_or_else(|_| {
+                        panic!("expected AnyInstance::Shared to downcast into Arc<Mutex<{}>>",
+                               any::type_name::<T>())
+                    }))
             }
         }
     }
 }
 
+/// Error returned instead of panicking when a `Mutex<T>` was poisoned by a panic
+/// in another thread.
+pub fn poisoned<T: Any>() -> Box<error::Error> {
+    format!("failed to lock {}, mutex was poisoned", any::type_name::<T>()).into()
+}
+
 pub struct Constructed {
     pub children: Vec<Box<Any>>,
 }
@@ -1,8 +1,9 @@
-use std::any::{Any, TypeId};
+use std::any::{self, Any, TypeId};
+use std::fmt;
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::collections::hash_map::Entry;
-use constructed::{Constructed, ConstructedShared, AnyInstance};
+use constructed::{Constructed, ConstructedShared, AnyInstance, poisoned};
 use inceptor::{Inceptor, Destructor};
 use {Result, Collection, Scope};
 
@@ -21,12 +22,18 @@ pub struct Deps {
                                 Vec<Box<Fn(&Deps, &mut AnyInstance) -> Result<()> + Send + Sync>>>,
     /// List of inceptors that manage shared dependency bridge creation for type pairs.
     inceptors: HashMap<(TypeId, TypeId), Box<Any>>,
+    /// Values of any type stored for constructors to read, one per type.
+    extensions: HashMap<TypeId, Box<Any>>,
+    /// Names of all types used as keys above, for debug output.
+    type_names: HashMap<TypeId, &'static str>,
 }
 
 fn to_shared<T: Any>(not_shared: Box<Any>) -> Box<Any> {
     let parent: T = *not_shared.downcast::<T>()
-        .expect("expected downcast to P when \
-                 changing to shared P");
+        .unwrap_or_else(|_| {
+            panic!("expected downcast to {0} when changing to shared {0}",
+                   any::type_name::<T>())
+        });
     Box::new(Arc::new(Mutex::new(parent)))
 }
 
@@ -38,9 +45,43 @@ impl Deps {
             shared_constructors: HashMap::new(),
             type_scope_created: HashMap::new(),
             inceptors: HashMap::new(),
+            extensions: HashMap::new(),
+            type_names: HashMap::new(),
         }
     }
 
+    /// Store a value that is not a dependency itself but should be available
+    /// to constructors, such as a handle to a runtime or metrics registry.
+    ///
+    /// Only one value per type is kept, and the previous one is returned.
+    pub fn insert_extension<T>(&mut self, value: T) -> Option<T>
+        where T: 'static + Any + Send + Sync
+    {
+        self.remember_type_name::<T>();
+        self.extensions
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|previous| {
+                *previous.downcast::<T>()
+                    .unwrap_or_else(|_| {
+                        panic!("expected extension to downcast into {}",
+                               any::type_name::<T>())
+                    })
+            })
+    }
+
+    /// Get the value of type `T` stored with `insert_extension`.
+    pub fn extension<T: Any>(&self) -> Option<&T> {
+        self.extensions
+            .get(&TypeId::of::<T>())
+            .map(|value| {
+                value.downcast_ref::<T>()
+                    .unwrap_or_else(|| {
+                        panic!("expected extension to downcast into {}",
+                               any::type_name::<T>())
+                    })
+            })
+    }
+
     /// Create dependencies for specified `obj` and return a wrapper `Scope` object.
     ///
     /// The wrapper `Scope` keeps ownership of all children together with parent object.
@@ -55,10 +96,13 @@ impl Deps {
         self.create(Collection::new()).map(|v| v.explode())
     }
 
+    /// Run `action` on every value of type `T` after its dependencies are created,
+    /// no matter whether it was passed to `create` or constructed as a child.
     pub fn when_ready<T, F>(&mut self, action: F)
         where T: 'static + Any,
               F: for<'r> Fn(&Deps, &mut T) -> Result<()> + 'static + Send + Sync
     {
+        self.remember_type_name::<T>();
         match self.type_scope_created.entry(TypeId::of::<T>()) {
             Entry::Occupied(mut list) => {
                 list.get_mut().push(into_action_with_deps(action));
@@ -95,7 +139,11 @@ impl Deps {
             Entry::Occupied(entry) => {
                 entry.get()
                     .downcast_ref::<Arc<Mutex<Inceptor<P1, P2>>>>()
-                    .expect("expected to find Inceptor of correct type in map")
+                    .unwrap_or_else(|| {
+                        panic!("expected to find Inceptor<{}, {}> in map",
+                               any::type_name::<P1>(),
+                               any::type_name::<P2>())
+                    })
                     .clone()
             }
             Entry::Vacant(entry) => {
@@ -118,10 +166,12 @@ impl Deps {
                 Box::new(|inceptor: &Arc<Mutex<Inceptor<P1, P2>>>, parent: &mut Box<Any>|
                 {
                     let parent_for_inceptor = parent.downcast_mut::<Arc<Mutex<P1>>>()
-                        .expect("expected downcast P1")
+                        .unwrap_or_else(|| {
+                            panic!("expected downcast to Arc<Mutex<{}>>",
+                                   any::type_name::<P1>())
+                        })
                         .clone();
-                    inceptor.lock()
-                        .expect("failed to lock ic1")
+                    try!(inceptor.lock().map_err(|_| poisoned::<Inceptor<P1, P2>>()))
                         .incept_1(parent_for_inceptor)
                 }),
                 1
@@ -133,10 +183,12 @@ impl Deps {
                 Box::new(|inceptor: &Arc<Mutex<Inceptor<P1, P2>>>, parent: &mut Box<Any>|
                 {
                     let parent_for_inceptor = parent.downcast_mut::<Arc<Mutex<P2>>>()
-                        .expect("expected downcast P2")
+                        .unwrap_or_else(|| {
+                            panic!("expected downcast to Arc<Mutex<{}>>",
+                                   any::type_name::<P2>())
+                        })
                         .clone();
-                    inceptor.lock()
-                        .expect("failed to lock ic2")
+                    try!(inceptor.lock().map_err(|_| poisoned::<Inceptor<P1, P2>>()))
                         .incept_2(parent_for_inceptor)
                 }),
                 2
@@ -213,6 +265,7 @@ impl Deps {
     fn register_isolated_constructor<P: Any>(&mut self,
                                              any_constructor: Box<Fn(&Deps, &mut Box<Any>)
                                                                      -> Result<Constructed> + Send + Sync>) {
+        self.remember_type_name::<P>();
         match self.isolated_constructors.entry(TypeId::of::<P>()) {
             Entry::Occupied(mut list) => {
                 list.get_mut().push(any_constructor);
@@ -228,6 +281,7 @@ impl Deps {
     fn register_shared_constructor<P: Any>(&mut self,
                                            any_constructor: Box<Fn(&Deps, &mut Box<Any>)
                                                                    -> Result<ConstructedShared> + Send + Sync>) {
+        self.remember_type_name::<P>();
         match self.shared_constructors.entry(TypeId::of::<P>()) {
             Entry::Occupied(mut list) => {
                 list.get_mut().push(any_constructor);
@@ -237,6 +291,46 @@ impl Deps {
             }
         };
     }
+
+    fn remember_type_name<T: Any>(&mut self) {
+        self.type_names.insert(TypeId::of::<T>(), any::type_name::<T>());
+    }
+
+    fn type_name_of(&self, type_id: &TypeId) -> &'static str {
+        self.type_names.get(type_id).cloned().unwrap_or("<unknown>")
+    }
+
+    fn count_by_type_name<V>(&self,
+                             map: &HashMap<TypeId, Vec<V>>)
+                             -> BTreeMap<&'static str, usize> {
+        map.iter()
+            .map(|(type_id, list)| (self.type_name_of(type_id), list.len()))
+            .collect()
+    }
+}
+
+impl fmt::Debug for Deps {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut bridges: Vec<_> = self.inceptors
+            .keys()
+            .map(|&(p1, p2)| (self.type_name_of(&p1), self.type_name_of(&p2)))
+            .collect();
+        bridges.sort();
+
+        let mut extensions: Vec<_> = self.extensions
+            .keys()
+            .map(|type_id| self.type_name_of(type_id))
+            .collect();
+        extensions.sort();
+
+        f.debug_struct("Deps")
+            .field("isolated_constructors", &self.count_by_type_name(&self.isolated_constructors))
+            .field("shared_constructors", &self.count_by_type_name(&self.shared_constructors))
+            .field("type_scope_created", &self.count_by_type_name(&self.type_scope_created))
+            .field("bridges", &bridges)
+            .field("extensions", &extensions)
+            .finish()
+    }
 }
 
 unsafe impl Send for Deps {}
@@ -252,14 +346,20 @@ fn into_action_with_deps<P, F>(action: F)
             AnyInstance::Isolated(ref mut value) => {
                 try!(action(deps,
                             &mut value.downcast_mut::<P>()
-                                .expect("expected to downcast type in post create action")))
+                                .unwrap_or_else(|| {
+                                    panic!("expected to downcast to {} in post create action",
+                                           any::type_name::<P>())
+                                })))
             }
             AnyInstance::Shared(ref mut value) => {
-                try!(action(deps,
-                            &mut value.downcast_mut::<Arc<Mutex<P>>>()
-                                .expect("expected to downcast type in post create action")
-                                .lock()
-                                .expect("expected to lock value for AnyInstance::Shared action")))
+                let mut locked = try!(value.downcast_mut::<Arc<Mutex<P>>>()
+                    .unwrap_or_else(|| {
+                        panic!("expected to downcast to Arc<Mutex<{}>> in post create action",
+                               any::type_name::<P>())
+                    })
+                    .lock()
+                    .map_err(|_| poisoned::<P>()));
+                try!(action(deps, &mut locked))
             }
         };
         Ok(())
@@ -280,6 +380,10 @@ fn into_shared_constructor<P1, P2, C>
     Box::new(move |deps: &Deps, parent: &mut Box<Any>| -> Result<ConstructedShared> {
         let (id, instances) = try!(incept_fun(&inceptor, parent));
 
+        // Created before children, so that the parent is removed from the inceptor
+        // if any child fails to be created.
+        let destructor = Destructor::new(inceptor.clone(), index, id);
+
         let mut children: Vec<Box<Any>> = Vec::with_capacity(instances.len() + 1);
 
         for instance in instances {
@@ -288,7 +392,7 @@ fn into_shared_constructor<P1, P2, C>
             children.push(Box::new(instance_artifacts));
         }
 
-        children.push(Box::new(Destructor::new(inceptor.clone(), index, id)));
+        children.push(Box::new(destructor));
 
         Ok(ConstructedShared { children: children })
     })
@@ -304,7 +408,11 @@ fn into_isolated_constructor_with_child_deps<P, C, F>
     Box::new(move |deps: &Deps, parent: &mut Box<Any>| -> Result<Constructed> {
         let child = {
             let concrete_parent = parent.downcast_mut::<P>()
-                .expect("expected to downcast type in into_isolated_constructor_with_child_deps");
+                .unwrap_or_else(|| {
+                    panic!("expected to downcast to {} in \
+                            into_isolated_constructor_with_child_deps",
+                           any::type_name::<P>())
+                });
             try!(deps.create(try!(constructor(deps, concrete_parent))))
         };
         Ok(Constructed { children: vec![Box::new(child)] })
@@ -321,8 +429,11 @@ fn into_isolated_constructor_with_ignored_child_deps<P, C, F>
     Box::new(move |deps: &Deps, parent: &mut Box<Any>| -> Result<Constructed> {
         try!(constructor(deps,
                          parent.downcast_mut::<P>()
-                             .expect("expected to downcast type in \
-                                      into_isolated_constructor_with_ignored_child_deps")));
+                             .unwrap_or_else(|| {
+                                 panic!("expected to downcast to {} in \
+                                         into_isolated_constructor_with_ignored_child_deps",
+                                        any::type_name::<P>())
+                             })));
         Ok(Constructed { children: vec![] })
     })
 }
@@ -336,8 +447,11 @@ fn into_isolated_constructor_without_child_deps<P, F>
     Box::new(move |deps: &Deps, parent: &mut Box<Any>| -> Result<Constructed> {
         constructor(deps,
                     parent.downcast_mut::<P>()
-                        .expect("expected to downcast type in \
-                                 into_isolated_constructor_without_child_deps"));
+                        .unwrap_or_else(|| {
+                            panic!("expected to downcast to {} in \
+                                    into_isolated_constructor_without_child_deps",
+                                   any::type_name::<P>())
+                        }));
         Ok(Constructed { children: vec![] })
     })
 }
@@ -345,6 +459,8 @@ fn into_isolated_constructor_without_child_deps<P, F>
 #[cfg(test)]
 mod test {
     use Deps;
+    use std::any;
+    use std::panic;
     use std::thread;
     use std::sync::{Arc, Mutex};
 
@@ -420,6 +536,165 @@ mod test {
         assert_eq!("Hi!", al.0);
     }
 
+    #[test]
+    fn constructor_can_read_extension() {
+        let mut deps = Deps::new();
+
+        assert_eq!(None, deps.insert_extension(C("first".into())));
+        assert_eq!(Some(C("first".into())), deps.insert_extension(C("ext".into())));
+        assert!(deps.extension::<B>().is_none());
+
+        let created_b_ref = Arc::new(Mutex::new(None));
+
+        deps.attach({
+            let created_b_ref = created_b_ref.clone();
+            move |deps: &Deps, a: &mut A| {
+                let c = deps.extension::<C>().expect("expected extension C");
+                let b = B([&a.0[..], "+", &c.0[..]].concat());
+                *created_b_ref.lock().unwrap() = Some(b.clone());
+                Ok(b)
+            }
+        });
+
+        deps.create(A("Hello".into())).unwrap();
+
+        assert_eq!("Hello+ext",
+                   (*created_b_ref.lock().unwrap()).clone().unwrap().0);
+    }
+
+    #[test]
+    fn runs_when_ready_for_every_value_of_type() {
+        let mut deps = Deps::new();
+
+        let ready_b_ref = Arc::new(Mutex::new(Vec::new()));
+
+        deps.attach(|_: &Deps, a: &mut A| Ok(B([&a.0[..], "+B"].concat())));
+
+        deps.when_ready({
+            let ready_b_ref = ready_b_ref.clone();
+            move |_: &Deps, b: &mut B| {
+                b.0.push_str("+ready");
+                ready_b_ref.lock().unwrap().push(b.clone());
+                Ok(())
+            }
+        });
+
+        deps.create(A("Hello".into())).unwrap();
+        let b = deps.create(B("World".into())).unwrap();
+
+        assert_eq!(B("World+ready".into()), b.explode());
+        assert_eq!(vec![B("Hello+B+ready".into()), B("World+ready".into())],
+                   *ready_b_ref.lock().unwrap());
+    }
+
+    #[test]
+    fn debug_lists_registrations_by_type_name() {
+        let mut deps = Deps::new();
+
+        deps.attach(|_: &Deps, a: &mut A| Ok(B(a.0.clone())));
+        deps.attach(|_: &Deps, _: &mut A| Ok(()));
+        deps.bridge(|_: &mut A, _: &mut B| Ok(()));
+        deps.when_ready(|_: &Deps, _: &mut C| Ok(()));
+        deps.insert_extension(5u32);
+
+        let a = any::type_name::<A>();
+        let b = any::type_name::<B>();
+        let c = any::type_name::<C>();
+
+        assert_eq!(format!("Deps {{ isolated_constructors: {{{:?}: 2}}, \
+                            shared_constructors: {{{:?}: 1, {:?}: 1}}, \
+                            type_scope_created: {{{:?}: 1}}, \
+                            bridges: [({:?}, {:?})], \
+                            extensions: [\"u32\"] }}",
+                           a, a, b, c, a, b),
+                   format!("{:?}", deps));
+    }
+
+    #[test]
+    fn collects_registered_items() {
+        let mut deps = Deps::new();
+
+        assert!(deps.collect::<A>().unwrap().is_empty());
+
+        deps.collectable(|_: &Deps| A("first".into()));
+        deps.collectable(|_: &Deps| A("second".into()));
+
+        let collection = deps.collect::<A>().unwrap();
+
+        assert_eq!(2, collection.len());
+        assert_eq!(&[A("first".into()), A("second".into())], collection.as_ref());
+    }
+
+    #[test]
+    fn returns_error_when_bridged_parent_is_poisoned() {
+        let mut deps = Deps::new();
+
+        let created_bridges = Arc::new(Mutex::new(Vec::new()));
+
+        deps.bridge(|a: &mut A, b: &mut B| Ok(vec![a.0.clone(), b.0.clone()]));
+
+        deps.when_ready({
+            let created_bridges = created_bridges.clone();
+            move |_: &Deps, parent: &mut Vec<String>| {
+                created_bridges.lock().unwrap().push(parent.concat());
+                Ok(())
+            }
+        });
+
+        let mut a = deps.create(A("Hello".into())).unwrap();
+
+        let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _guard = a.lock().unwrap();
+            panic!("poison A");
+        }));
+
+        assert!(deps.create(B("Failed".into())).is_err());
+
+        assert_eq!(A("Hello".into()), a.explode());
+
+        // B that failed to be created should not be bridged with new A
+        let _a = deps.create(A("Fresh".into())).unwrap();
+
+        assert!(created_bridges.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn does_not_bridge_parent_whose_bridged_child_failed() {
+        let mut deps = Deps::new();
+
+        deps.bridge(|a: &mut A, b: &mut B| Ok(C([&a.0[..], "+", &b.0[..]].concat())));
+
+        deps.attach(|_: &Deps, c: &mut C| {
+            if c.0.ends_with("bad") {
+                return Err("child failed".into());
+            }
+            Ok(())
+        });
+
+        let _a = deps.create(A("a1".into())).unwrap();
+
+        assert!(deps.create(B("bad".into())).is_err());
+
+        // B that failed to be created should not be bridged with new A
+        assert!(deps.create(A("a2".into())).is_ok());
+    }
+
+    #[test]
+    fn drops_bridged_scope_after_bridge_panicked() {
+        let mut deps = Deps::new();
+
+        deps.bridge(|_: &mut A, _: &mut B| -> ::Result<()> { panic!("bridge failed") });
+
+        let a = deps.create(A("Hello".into())).unwrap();
+
+        assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                deps.create(B("World".into()))
+            }))
+            .is_err());
+
+        drop(a);
+    }
+
     #[test]
     fn should_work_accross_threads() {
         let mut deps = Deps::new();
@@ -3,6 +3,7 @@
 use std::sync::{Arc, Mutex};
 use std::any::Any;
 use std::mem;
+use constructed::poisoned;
 use Result;
 
 pub struct Inceptor<T1, T2> {
@@ -58,8 +59,8 @@ impl<T1: Any, T2: Any> Inceptor<T1, T2> {
             Some(ref mut val) => val,
             None => unreachable!("expected i2 to exist at slot"),
         };
-        let mut locked1 = val1.lock().expect("expected to lock val1");
-        let mut locked2 = val2.lock().expect("expected to lock val2");
+        let mut locked1 = try!(val1.lock().map_err(|_| poisoned::<T1>()));
+        let mut locked2 = try!(val2.lock().map_err(|_| poisoned::<T2>()));
         (self.constructor)(&mut locked1, &mut locked2)
     }
 
@@ -68,9 +69,14 @@ impl<T1: Any, T2: Any> Inceptor<T1, T2> {
         let mut results = Vec::new();
         for i2 in 0..self.d2.len() {
             if !self.d2[i2].is_none() {
-                match try!(self.invoke(id, i2)) {
-                    Some(res) => results.push(res),
-                    None => (),
+                match self.invoke(id, i2) {
+                    Ok(Some(res)) => results.push(res),
+                    Ok(None) => (),
+                    Err(e) => {
+                        // No destructor will be created for a parent that failed.
+                        self.destroy_1(id);
+                        return Err(e);
+                    }
                 }
             }
         }
@@ -82,9 +88,14 @@ impl<T1: Any, T2: Any> Inceptor<T1, T2> {
         let mut results = Vec::new();
         for i1 in 0..self.d1.len() {
             if !self.d1[i1].is_none() {
-                match try!(self.invoke(i1, id)) {
-                    Some(res) => results.push(res),
-                    None => (),
+                match self.invoke(i1, id) {
+                    Ok(Some(res)) => results.push(res),
+                    Ok(None) => (),
+                    Err(e) => {
+                        // No destructor will be created for a parent that failed.
+                        self.destroy_2(id);
+                        return Err(e);
+                    }
                 }
             }
         }
@@ -173,10 +184,12 @@ impl<T1: Any, T2: Any> Destructor<T1, T2> {
 
 impl<T1: Any, T2: Any> Drop for Destructor<T1, T2> {
     fn drop(&mut self) {
+        // A panic in the bridge constructor poisons the inceptor, but freeing a slot
+        // is still safe, and panicking here could abort while that panic unwinds.
         if self.index == 1 {
-            self.inceptor.lock().expect("failed to lock").destroy_1(self.id);
+            self.inceptor.lock().unwrap_or_else(|e| e.into_inner()).destroy_1(self.id);
         } else if self.index == 2 {
-            self.inceptor.lock().expect("failed to lock").destroy_2(self.id);
+            self.inceptor.lock().unwrap_or_else(|e| e.into_inner()).destroy_2(self.id);
         }
     }
 }
@@ -30,7 +30,7 @@ impl<T: Any> Scope<T> {
                     .ok()
                     .expect("expected arc to be last remaining")
                     .into_inner()
-                    .expect("expected to lock value before exploding")
+                    .unwrap_or_else(|e| e.into_inner())
             }
         }
     }